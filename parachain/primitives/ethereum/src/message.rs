@@ -1,7 +1,11 @@
 use ethereum_types::{H160, U256};
 use ethabi::{Event, Param, ParamKind, Token};
+use keccak_hash::keccak;
 
+use crate::header::Header;
 use crate::log::Log;
+use crate::mpt::{self, ProofError};
+use crate::receipt::Receipt;
 
 static EVENT_ABI: &'static Event = &Event {
 	signature: "AppEvent(uint256,bytes)",
@@ -37,6 +41,14 @@ pub enum DecodeError {
 	InvalidAddress,
 	// Invalid message payload
 	InvalidPayload,
+	// Log was not proven to be included in the block
+	InvalidProof(ProofError),
+}
+
+impl From<ProofError> for DecodeError {
+	fn from(err: ProofError) -> Self {
+		DecodeError::InvalidProof(err)
+	}
 }
 
 impl From<rlp::DecoderError> for DecodeError {
@@ -87,45 +99,126 @@ pub enum Message {
 
 impl Message {
 
+	/// Decode a `Message`, first proving that `log` was actually emitted by
+	/// the given transaction within `header`'s block.
+	///
+	/// `receipt_proof` is the list of RLP-encoded trie nodes, in root-to-leaf
+	/// order, proving inclusion of transaction `tx_index`'s receipt in
+	/// `header.receipts_root`. This lets a relayer submit logs without the
+	/// runtime having to trust the relayer: the log is verified against the
+	/// already-trusted block header before anything is decoded from it.
+	pub fn decode_verified(
+		header: &Header,
+		receipt_proof: &[Vec<u8>],
+		tx_index: u32,
+		log: Log,
+	) -> Result<Self, DecodeError> {
+		let key = rlp::encode(&tx_index);
+		let receipt_rlp = mpt::verify_proof(header.receipts_root, &key, receipt_proof)?;
+		let receipt: Receipt = rlp::decode(&receipt_rlp)?;
+
+		if !receipt.contains_log(&log) {
+			return Err(DecodeError::InvalidProof(ProofError::KeyNotFound));
+		}
+
+		Self::decode(log)
+	}
+
+	/// Decode a `Message` straight from the RLP a relayer submits on the
+	/// wire, without panicking on malformed input.
+	///
+	/// `Log` itself arrives RLP-encoded from an untrusted Ethereum peer, so
+	/// this threads the fallible `rlp` decode through `DecodeError` and
+	/// checks the result looks like a genuine `AppEvent` before proceeding,
+	/// so that one bad payload in a stream can't take down the relayer.
+	pub fn decode_from_rlp(raw: &[u8]) -> Result<Self, DecodeError> {
+		let log: Log = rlp::decode(raw)?;
+		Self::validate_log(&log)?;
+		Self::decode(log)
+	}
+
+	/// Decode every `AppEvent` among `logs`, skipping logs that belong to
+	/// other contracts/events instead of erroring on them.
+	///
+	/// A relayer watching the bridge contract receives whole transaction
+	/// receipts, not individual pre-filtered logs, so this does the
+	/// `EVENT_ABI` signature filtering itself and returns one decode result
+	/// per matching log, preserving individual errors for diagnostics.
+	pub fn decode_receipt(logs: &[Log]) -> Vec<Result<Self, DecodeError>> {
+		let signature_topic = keccak(EVENT_ABI.signature.as_bytes());
+		logs.iter()
+			.filter(|log| log.topics.first() == Some(&signature_topic))
+			.map(|log| Self::decode(log.clone()))
+			.collect()
+	}
+
+	/// Structural invariants a genuine `AppEvent` log must satisfy, checked
+	/// before attempting the ABI decode. Returns `InvalidPayload` rather
+	/// than `InvalidData`, since `DecodeError::InvalidData` wraps an
+	/// `ethabi::Error` we have no such error to construct here.
+	fn validate_log(log: &Log) -> Result<(), DecodeError> {
+		let signature_topic = keccak(EVENT_ABI.signature.as_bytes());
+		if log.topics.first() != Some(&signature_topic) {
+			return Err(DecodeError::InvalidPayload);
+		}
+		// `(uint256, bytes)` ABI-encodes to at least two 32-byte words: the
+		// tag, and the dynamic bytes' offset/length header.
+		if log.data.is_empty() || log.data.len() % 32 != 0 {
+			return Err(DecodeError::InvalidPayload);
+		}
+		Ok(())
+	}
+
 	pub fn decode(log: Log) -> Result<Self, DecodeError> {
-		let tokens = EVENT_ABI.decode(log.topics, log.data)?;
-	
-		let mut tokens_iter = tokens.iter();
-	
-		// extract message tag ("sendETH" or "sendERC20")
-		let tag = match tokens_iter.next().ok_or(DecodeError::InvalidPayload)? {
-			Token::Uint(value) => value.low_u32() as u8,
-			_ => return Err(DecodeError::InvalidPayload)
-		};
-		
-		// extract ABI-encoded message payload
-		let payload = match tokens_iter.next().ok_or(DecodeError::InvalidPayload)? {
-			Token::Bytes(bytes) => Self::decode_payload(&bytes)?,
-			_ => return Err(DecodeError::InvalidPayload)
-        };
+		let decoded = Registry::with_defaults().decode(log)?;
+		decoded
+			.as_any()
+			.downcast_ref::<Message>()
+			.copied()
+			.ok_or(DecodeError::InvalidTag)
+	}
 
-		match tag {
-			TAG_SENDETH => {
-				Ok(Message::SendETH {
-					sender: payload.sender,
-					recipient: payload.recipient,
-					amount: payload.amount,
-					nonce: payload.nonce,
-				})
-			},
-			TAG_SENDERC20 => {
-				Ok(Message::SendERC20 {
-					sender: payload.sender,
-					recipient: payload.recipient,
-					token: payload.token,
-					amount: payload.amount,
-					nonce: payload.nonce,
-				})
-			}
-			_ => { return Err(DecodeError::InvalidPayload) }
-        }
+	/// ABI-encode this message back into `AppEvent(uint256,bytes)` event
+	/// data, the inverse of `decode`.
+	pub fn encode(&self) -> Vec<u8> {
+		let (tag, payload) = self.encode_payload();
+		ethabi::encode(&[Token::Uint(tag.into()), Token::Bytes(payload)])
+	}
 
-    }
+	/// Wrap this message's encoded event data in a `Log` as it would have
+	/// been emitted on Ethereum, for bridge tooling and round-trip tests.
+	pub fn to_log(&self) -> Log {
+		Log {
+			address: H160::zero(),
+			topics: vec![keccak(EVENT_ABI.signature.as_bytes())],
+			data: self.encode(),
+		}
+	}
+
+	fn encode_payload(&self) -> (u8, Vec<u8>) {
+		match *self {
+			Message::SendETH { sender, recipient, amount, nonce } => {
+				let payload = ethabi::encode(&[
+					Token::Address(sender),
+					Token::FixedBytes(recipient.to_vec()),
+					Token::Address(H160::zero()),
+					Token::Uint(amount),
+					Token::Uint(nonce.into()),
+				]);
+				(TAG_SENDETH, payload)
+			},
+			Message::SendERC20 { sender, recipient, token, amount, nonce } => {
+				let payload = ethabi::encode(&[
+					Token::Address(sender),
+					Token::FixedBytes(recipient.to_vec()),
+					Token::Address(token),
+					Token::Uint(amount),
+					Token::Uint(nonce.into()),
+				]);
+				(TAG_SENDERC20, payload)
+			},
+		}
+	}
 
     fn decode_payload(data: &[u8]) -> Result<Payload, DecodeError> {
 
@@ -174,6 +267,110 @@ impl Message {
     }
 }
 
+/// A decoded application message, type-erased so the `Registry` can hand
+/// it back without knowing the concrete payload schema that produced it.
+pub trait BridgedMessage: core::fmt::Debug {
+	fn as_any(&self) -> &dyn core::any::Any;
+}
+
+impl BridgedMessage for Message {
+	fn as_any(&self) -> &dyn core::any::Any {
+		self
+	}
+}
+
+/// Decodes one bridged application's payload, identified by its tag byte.
+///
+/// Implement this to support an `AppEvent` payload schema this crate
+/// doesn't know about, and register it with a `Registry` instead of
+/// forking this file.
+pub trait AppMessageDecoder {
+	fn tag(&self) -> u8;
+	fn decode_payload(&self, bytes: &[u8]) -> Result<Box<dyn BridgedMessage>, DecodeError>;
+}
+
+struct SendETHDecoder;
+
+impl AppMessageDecoder for SendETHDecoder {
+	fn tag(&self) -> u8 {
+		TAG_SENDETH
+	}
+
+	fn decode_payload(&self, bytes: &[u8]) -> Result<Box<dyn BridgedMessage>, DecodeError> {
+		let payload = Message::decode_payload(bytes)?;
+		Ok(Box::new(Message::SendETH {
+			sender: payload.sender,
+			recipient: payload.recipient,
+			amount: payload.amount,
+			nonce: payload.nonce,
+		}))
+	}
+}
+
+struct SendERC20Decoder;
+
+impl AppMessageDecoder for SendERC20Decoder {
+	fn tag(&self) -> u8 {
+		TAG_SENDERC20
+	}
+
+	fn decode_payload(&self, bytes: &[u8]) -> Result<Box<dyn BridgedMessage>, DecodeError> {
+		let payload = Message::decode_payload(bytes)?;
+		Ok(Box::new(Message::SendERC20 {
+			sender: payload.sender,
+			recipient: payload.recipient,
+			token: payload.token,
+			amount: payload.amount,
+			nonce: payload.nonce,
+		}))
+	}
+}
+
+/// Maps `AppEvent` tag bytes to the decoder for that application, so a new
+/// bridged application can be supported by registering a decoder rather
+/// than editing this crate.
+#[derive(Default)]
+pub struct Registry {
+	decoders: Vec<Box<dyn AppMessageDecoder>>,
+}
+
+impl Registry {
+	pub fn new() -> Self {
+		Registry { decoders: Vec::new() }
+	}
+
+	/// A registry pre-populated with this crate's built-in applications.
+	pub fn with_defaults() -> Self {
+		let mut registry = Self::new();
+		registry.register(Box::new(SendETHDecoder));
+		registry.register(Box::new(SendERC20Decoder));
+		registry
+	}
+
+	pub fn register(&mut self, decoder: Box<dyn AppMessageDecoder>) {
+		self.decoders.push(decoder);
+	}
+
+	/// Decode `log` by extracting its tag and dispatching to the decoder
+	/// registered for that tag.
+	pub fn decode(&self, log: Log) -> Result<Box<dyn BridgedMessage>, DecodeError> {
+		let tokens = EVENT_ABI.decode(log.topics, log.data)?;
+		let mut tokens_iter = tokens.iter();
+
+		let tag = match tokens_iter.next().ok_or(DecodeError::InvalidPayload)? {
+			Token::Uint(value) => value.low_u32() as u8,
+			_ => return Err(DecodeError::InvalidPayload),
+		};
+
+		let payload = match tokens_iter.next().ok_or(DecodeError::InvalidPayload)? {
+			Token::Bytes(bytes) => bytes,
+			_ => return Err(DecodeError::InvalidPayload),
+		};
+
+		let decoder = self.decoders.iter().find(|d| d.tag() == tag).ok_or(DecodeError::InvalidTag)?;
+		decoder.decode_payload(payload)
+	}
+}
 
 #[cfg(test)]
 mod tests {
@@ -210,4 +407,114 @@ mod tests {
             }
         );
     }
+
+    #[test]
+    fn test_encode_decode_round_trip() {
+        let messages = vec![
+            Message::SendETH {
+                sender: "cffeaaf7681c89285d65cfbe808b80e502696573".parse().unwrap(),
+                recipient: recipient("8eaf04151687736326c9fea17e25fc5287613693c912909cb226aa4794f26a48"),
+                amount: 10.into(),
+                nonce: 7,
+            },
+            Message::SendERC20 {
+                sender: "cffeaaf7681c89285d65cfbe808b80e502696573".parse().unwrap(),
+                recipient: recipient("8eaf04151687736326c9fea17e25fc5287613693c912909cb226aa4794f26a48"),
+                token: "d2db8e7b1f40de44bea1878a327a2d4b9d32c7cb".parse().unwrap(),
+                amount: 500.into(),
+                nonce: 3,
+            },
+        ];
+
+        for message in messages {
+            let log = message.to_log();
+            assert_eq!(Message::decode(log).unwrap(), message);
+        }
+    }
+
+    #[test]
+    fn test_decode_from_rlp_rejects_garbage() {
+        let garbage = vec![0xff, 0x00, 0x01];
+
+        match Message::decode_from_rlp(&garbage) {
+            Err(DecodeError::InvalidRLP(_)) => (),
+            other => panic!("expected InvalidRLP, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_decode_from_rlp_rejects_wrong_topic() {
+        let message = Message::SendETH {
+            sender: "cffeaaf7681c89285d65cfbe808b80e502696573".parse().unwrap(),
+            recipient: recipient("8eaf04151687736326c9fea17e25fc5287613693c912909cb226aa4794f26a48"),
+            amount: 10.into(),
+            nonce: 7,
+        };
+        let mut log = message.to_log();
+        log.topics = vec![keccak(b"SomeOtherEvent(uint256)")];
+        let raw = rlp::encode(&log);
+
+        match Message::decode_from_rlp(&raw) {
+            Err(DecodeError::InvalidPayload) => (),
+            other => panic!("expected InvalidPayload, got {:?}", other),
+        }
+    }
+
+    #[derive(Debug)]
+    struct Custom { marker: u32 }
+
+    impl BridgedMessage for Custom {
+        fn as_any(&self) -> &dyn std::any::Any { self }
+    }
+
+    struct CustomDecoder;
+
+    impl AppMessageDecoder for CustomDecoder {
+        fn tag(&self) -> u8 { 2 }
+
+        fn decode_payload(&self, bytes: &[u8]) -> Result<Box<dyn BridgedMessage>, DecodeError> {
+            if bytes.len() != 4 {
+                return Err(DecodeError::InvalidPayload);
+            }
+            let mut buf = [0u8; 4];
+            buf.copy_from_slice(bytes);
+            Ok(Box::new(Custom { marker: u32::from_be_bytes(buf) }))
+        }
+    }
+
+    #[test]
+    fn test_registry_dispatches_to_registered_decoder() {
+        let mut registry = Registry::new();
+        registry.register(Box::new(CustomDecoder));
+
+        let log = Log {
+            address: H160::zero(),
+            topics: vec![keccak(EVENT_ABI.signature.as_bytes())],
+            data: ethabi::encode(&[Token::Uint(2.into()), Token::Bytes(42u32.to_be_bytes().to_vec())]),
+        };
+
+        let decoded = registry.decode(log).unwrap();
+        let custom = decoded.as_any().downcast_ref::<Custom>().unwrap();
+        assert_eq!(custom.marker, 42);
+    }
+
+    #[test]
+    fn test_decode_receipt_skips_unrelated_logs() {
+        let message = Message::SendETH {
+            sender: "cffeaaf7681c89285d65cfbe808b80e502696573".parse().unwrap(),
+            recipient: recipient("8eaf04151687736326c9fea17e25fc5287613693c912909cb226aa4794f26a48"),
+            amount: 10.into(),
+            nonce: 7,
+        };
+        let unrelated = Log {
+            address: H160::zero(),
+            topics: vec![keccak(b"SomeOtherEvent(uint256)")],
+            data: vec![1, 2, 3],
+        };
+
+        let results = Message::decode_receipt(&[unrelated, message.to_log()]);
+
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].as_ref().unwrap(), &message);
+    }
 }
@@ -0,0 +1,259 @@
+//! Verification of Merkle-Patricia trie inclusion proofs.
+//!
+//! Ethereum commits transactions and receipts to per-block tries keyed by
+//! the RLP-encoded item index. This module walks a proof (the list of trie
+//! nodes on the path from the root to a leaf) and returns the leaf value,
+//! without trusting anything other than the trie root hash.
+
+use ethereum_types::H256;
+use keccak_hash::keccak;
+use rlp::Rlp;
+
+/// Why a Merkle-Patricia proof failed to verify.
+#[derive(Debug, PartialEq, Eq)]
+pub enum ProofError {
+	/// The first proof node does not hash to the expected trie root.
+	RootMismatch,
+	/// A proof node could not be RLP-decoded, or was neither a 2-item
+	/// leaf/extension nor a 17-item branch.
+	InvalidNode,
+	/// A branch or extension referenced a child node (by hash) that was
+	/// not supplied in the proof.
+	MissingNode,
+	/// The key was not found in the trie (a branch had no child for the
+	/// next nibble, or an extension/leaf's partial key did not match).
+	KeyNotFound,
+}
+
+/// Walk `proof` from the trie root down to the value addressed by `key`,
+/// verifying each step cryptographically, and return the value's raw bytes.
+///
+/// `root` is the trie root hash (e.g. a block header's `receiptsRoot`).
+/// `key` is the full, un-nibbled trie key (e.g. `rlp(tx_index)`). `proof`
+/// is the ordered list of RLP-encoded trie nodes visited along the path;
+/// nodes are addressed either by their keccak256 hash (the common case) or
+/// embedded directly in their parent (see `resolve_child`).
+pub fn verify_proof(root: H256, key: &[u8], proof: &[Vec<u8>]) -> Result<Vec<u8>, ProofError> {
+	let mut nodes = proof.iter();
+	let mut node_rlp = nodes.next().ok_or(ProofError::RootMismatch)?.clone();
+	if keccak(&node_rlp) != root {
+		return Err(ProofError::RootMismatch);
+	}
+
+	let nibbles = bytes_to_nibbles(key);
+	let mut offset = 0usize;
+
+	loop {
+		let node = Rlp::new(&node_rlp);
+		let item_count = node.item_count().map_err(|_| ProofError::InvalidNode)?;
+
+		let next_ref: Vec<u8> = match item_count {
+			17 => {
+				if offset == nibbles.len() {
+					let value = node.at(16).map_err(|_| ProofError::InvalidNode)?;
+					return value.data().map_err(|_| ProofError::InvalidNode).map(|d| d.to_vec());
+				}
+				let branch_item = node.at(nibbles[offset] as usize).map_err(|_| ProofError::InvalidNode)?;
+				if branch_item.is_empty() {
+					return Err(ProofError::KeyNotFound);
+				}
+				offset += 1;
+				branch_item.as_raw().to_vec()
+			}
+			2 => {
+				let (partial, is_leaf) = decode_compact(
+					node.at(0).map_err(|_| ProofError::InvalidNode)?.data().map_err(|_| ProofError::InvalidNode)?,
+				);
+				if nibbles[offset..].len() < partial.len() || nibbles[offset..offset + partial.len()] != partial[..] {
+					return Err(ProofError::KeyNotFound);
+				}
+				offset += partial.len();
+
+				let value = node.at(1).map_err(|_| ProofError::InvalidNode)?;
+				if is_leaf {
+					if offset != nibbles.len() {
+						return Err(ProofError::KeyNotFound);
+					}
+					return value.data().map_err(|_| ProofError::InvalidNode).map(|d| d.to_vec());
+				}
+				value.as_raw().to_vec()
+			}
+			_ => return Err(ProofError::InvalidNode),
+		};
+
+		node_rlp = resolve_child(&next_ref, &mut nodes)?;
+	}
+}
+
+/// Resolve a branch/extension child reference to its RLP-encoded node.
+///
+/// A child reference is either a keccak256 hash of the next node, which
+/// must be the next element of `nodes`, or the node embedded inline as a
+/// nested RLP list (used when the node's own encoding is short enough that
+/// hashing it would waste space). `child_ref` is itself the embedded node's
+/// encoding in the latter case, so it's recognised by being a list rather
+/// than a 32-byte string and is used as-is, with no keccak lookup.
+fn resolve_child<'a, I>(child_ref: &[u8], nodes: &mut I) -> Result<Vec<u8>, ProofError>
+where
+	I: Iterator<Item = &'a Vec<u8>>,
+{
+	let rlp = Rlp::new(child_ref);
+	if rlp.is_list() {
+		return Ok(child_ref.to_vec());
+	}
+
+	let hash = rlp.data().map_err(|_| ProofError::InvalidNode)?;
+	if hash.len() != 32 {
+		return Err(ProofError::InvalidNode);
+	}
+	let expected = H256::from_slice(hash);
+	let next = nodes.next().ok_or(ProofError::MissingNode)?;
+	if keccak(next) != expected {
+		return Err(ProofError::MissingNode);
+	}
+	Ok(next.clone())
+}
+
+/// Split a byte string into big-endian nibbles.
+fn bytes_to_nibbles(bytes: &[u8]) -> Vec<u8> {
+	let mut nibbles = Vec::with_capacity(bytes.len() * 2);
+	for byte in bytes {
+		nibbles.push(byte >> 4);
+		nibbles.push(byte & 0x0f);
+	}
+	nibbles
+}
+
+/// Decode a hex-prefix (compact) encoded partial key, returning the nibbles
+/// and whether the node it belongs to is a leaf (as opposed to an extension).
+fn decode_compact(compact: &[u8]) -> (Vec<u8>, bool) {
+	if compact.is_empty() {
+		return (Vec::new(), false);
+	}
+	let is_leaf = compact[0] & 0x20 != 0;
+	let is_odd = compact[0] & 0x10 != 0;
+
+	let mut nibbles = Vec::with_capacity(compact.len() * 2);
+	if is_odd {
+		nibbles.push(compact[0] & 0x0f);
+	}
+	for byte in &compact[1..] {
+		nibbles.push(byte >> 4);
+		nibbles.push(byte & 0x0f);
+	}
+	(nibbles, is_leaf)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use rlp::RlpStream;
+
+	/// Hex-prefix (compact) encode `nibbles` as a leaf (`is_leaf`) or
+	/// extension partial key, mirroring `decode_compact` in reverse.
+	fn encode_compact(nibbles: &[u8], is_leaf: bool) -> Vec<u8> {
+		let is_odd = nibbles.len() % 2 == 1;
+		let mut flag = if is_leaf { 0x20 } else { 0x00 };
+		if is_odd {
+			flag |= 0x10;
+		}
+
+		let mut out = Vec::new();
+		let mut rest = nibbles;
+		if is_odd {
+			out.push(flag | nibbles[0]);
+			rest = &nibbles[1..];
+		} else {
+			out.push(flag);
+		}
+		for pair in rest.chunks(2) {
+			out.push((pair[0] << 4) | pair[1]);
+		}
+		out
+	}
+
+	fn encode_leaf(partial_key: &[u8], value: &[u8]) -> Vec<u8> {
+		let mut stream = RlpStream::new_list(2);
+		stream.append(&encode_compact(partial_key, true));
+		stream.append(&value.to_vec());
+		stream.out().to_vec()
+	}
+
+	#[test]
+	fn test_verify_proof_single_leaf() {
+		let key = vec![0x12];
+		let value = b"leaf-value".to_vec();
+		let leaf = encode_leaf(&[1, 2], &value);
+		let root = keccak(&leaf);
+
+		assert_eq!(verify_proof(root, &key, &[leaf]), Ok(value));
+	}
+
+	#[test]
+	fn test_verify_proof_rejects_root_mismatch() {
+		let key = vec![0x12];
+		let leaf = encode_leaf(&[1, 2], b"leaf-value");
+
+		assert_eq!(
+			verify_proof(H256::zero(), &key, &[leaf]),
+			Err(ProofError::RootMismatch)
+		);
+	}
+
+	#[test]
+	fn test_verify_proof_branch_with_hashed_child() {
+		let key = vec![0x12];
+		let value = b"leaf-value".to_vec();
+		// After the branch consumes nibble `1` at offset 0, the child leaf
+		// only needs to match the remaining nibble `2`.
+		let child = encode_leaf(&[2], &value);
+		let child_hash = keccak(&child);
+
+		let mut branch = RlpStream::new_list(17);
+		for i in 0..17u8 {
+			if i == 1 {
+				branch.append(&child_hash.as_bytes().to_vec());
+			} else {
+				branch.append_empty_data();
+			}
+		}
+		let branch = branch.out().to_vec();
+		let root = keccak(&branch);
+
+		assert_eq!(
+			verify_proof(root, &key, &[branch.clone(), child]),
+			Ok(value)
+		);
+
+		// The same proof without the child node can't be verified: the
+		// branch only points at the child by hash, and that hash is never
+		// supplied.
+		assert_eq!(
+			verify_proof(root, &key, &[branch]),
+			Err(ProofError::MissingNode)
+		);
+	}
+
+	#[test]
+	fn test_verify_proof_branch_with_embedded_child() {
+		let key = vec![0x12];
+		let value = b"v".to_vec();
+		let child = encode_leaf(&[2], &value);
+		assert!(child.len() < 32, "embedded child must be short enough to inline");
+
+		let mut branch = RlpStream::new_list(17);
+		for i in 0..17u8 {
+			if i == 1 {
+				branch.append_raw(&child, 1);
+			} else {
+				branch.append_empty_data();
+			}
+		}
+		let branch = branch.out().to_vec();
+		let root = keccak(&branch);
+
+		// The child is embedded directly in the branch, so the proof never
+		// needs to supply it separately.
+		assert_eq!(verify_proof(root, &key, &[branch]), Ok(value));
+	}
+}
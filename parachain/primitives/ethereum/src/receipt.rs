@@ -0,0 +1,63 @@
+use rlp::{DecoderError, Rlp};
+
+use crate::log::Log;
+
+/// A transaction receipt, as committed to by a block header's `receiptsRoot`.
+///
+/// RLP-encoded as `[status, cumulativeGasUsed, bloom, logs]`, matching the
+/// post-Byzantium Ethereum receipt format.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Receipt {
+	pub status: bool,
+	pub cumulative_gas_used: u64,
+	pub bloom: Vec<u8>,
+	pub logs: Vec<Log>,
+}
+
+impl rlp::Decodable for Receipt {
+	fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+		Ok(Receipt {
+			status: rlp.val_at(0)?,
+			cumulative_gas_used: rlp.val_at(1)?,
+			bloom: rlp.val_at(2)?,
+			logs: rlp.list_at(3)?,
+		})
+	}
+}
+
+impl Receipt {
+	/// Whether `log` appears verbatim among this receipt's logs.
+	pub fn contains_log(&self, log: &Log) -> bool {
+		self.logs.iter().any(|l| l == log)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use ethereum_types::H160;
+
+	fn log(data: &[u8]) -> Log {
+		Log { address: H160::zero(), topics: vec![], data: data.to_vec() }
+	}
+
+	fn receipt(logs: Vec<Log>) -> Receipt {
+		Receipt { status: true, cumulative_gas_used: 21000, bloom: vec![0; 256], logs }
+	}
+
+	#[test]
+	fn test_contains_log_present() {
+		let target = log(b"included");
+		let receipt = receipt(vec![log(b"other"), target.clone()]);
+
+		assert!(receipt.contains_log(&target));
+	}
+
+	#[test]
+	fn test_contains_log_absent() {
+		let target = log(b"not-in-receipt");
+		let receipt = receipt(vec![log(b"other"), log(b"another")]);
+
+		assert!(!receipt.contains_log(&target));
+	}
+}